@@ -3,6 +3,7 @@ use std::fs::File;
 use std::io::{prelude::*, BufReader};
 
 use libakaza::lm::system_bigram::SystemBigramLMBuilder;
+use libakaza::lm::system_trigram::SystemTrigramLMBuilder;
 use libakaza::lm::system_unigram_lm::{SystemUnigramLM, SystemUnigramLMBuilder};
 
 // e.g.g 倉庫会社/そうこがいしゃ -6.973789593503506
@@ -10,19 +11,12 @@ fn process_unigram(srcpath: &String, dstpath: &String) {
     let file = File::open(srcpath).expect("Open {txtfile} correctly.");
 
     let mut builder = SystemUnigramLMBuilder::new();
-    let mut i: u64 = 0;
     for line in BufReader::new(file).lines() {
         let line = line.unwrap();
         let (word, score) = line.trim().split_once(' ').unwrap();
         let score: f32 = score.parse().unwrap();
 
         builder.add(&word.to_string(), score);
-
-        i += 1;
-        if i >= 8388608 {
-            // 3 byte に ID が収まる必要がある
-            panic!("too much words.");
-        }
     }
 
     println!("Writing {}", dstpath);
@@ -70,6 +64,52 @@ fn process_2gram(unigram: &SystemUnigramLM, srcpath: &String, dstpath: &String)
     builder.save(dstpath).unwrap();
 }
 
+// e.g. 倉庫/そうこ\t会社/かいしゃ\t設立/せつりつ -4.128904 (単語どうしはタブ区切り)
+fn process_3gram(unigram: &SystemUnigramLM, srcpath: &String, dstpath: &String) {
+    let file = File::open(srcpath).unwrap();
+
+    let mut builder = SystemTrigramLMBuilder::new();
+
+    for line in BufReader::new(file).lines() {
+        fn parse_3gram_line(line: &String) -> (String, String, String, f32) {
+            let tokens: Vec<&str> = line.split(' ').collect();
+            if tokens.len() != 2 {
+                println!("Invalid tokens: {:?}", tokens);
+                panic!()
+            }
+            let words: &str = tokens[0];
+            let score = tokens[1];
+
+            let mut it = words.split('\t');
+            let word1 = it.next().unwrap();
+            let word2 = it.next().unwrap();
+            let word3 = it.next().unwrap();
+            let score = score.parse().unwrap();
+            (word1.to_string(), word2.to_string(), word3.to_string(), score)
+        }
+
+        let line = line.unwrap();
+        let (word1, word2, word3, score) = parse_3gram_line(&line);
+
+        let Some((word_id1, _)) = unigram.find(&word1) else {
+            println!("Can't find '{}' in unigram data", word1);
+            continue;
+        };
+        let Some((word_id2, _)) = unigram.find(&word2) else {
+            println!("Can't find '{}' in unigram data", word2);
+            continue;
+        };
+        let Some((word_id3, _)) = unigram.find(&word3) else {
+            println!("Can't find '{}' in unigram data", word3);
+            continue;
+        };
+
+        builder.add(word_id1 as u32, word_id2 as u32, word_id3 as u32, score);
+    }
+
+    builder.save(dstpath).unwrap();
+}
+
 fn main() {
     // 1gram ファイルから読む。
     // 1gram の map<string, int> の ID mapping を作成する
@@ -98,5 +138,11 @@ fn main() {
     println!("Unigram system lm: {}", unigram_lm.num_keys());
     process_2gram(&unigram_lm, bigram_src, bigram_dst);
 
+    // 3gram ファイルは任意。渡されていれば読んで、渡されていなければ bigram までで終わる。
+    if let (Some(trigram_src), Some(trigram_dst)) = (args.get(5), args.get(6)) {
+        println!("Trigram {} to {}", trigram_src, trigram_dst);
+        process_3gram(&unigram_lm, trigram_src, trigram_dst);
+    }
+
     println!("DONE");
 }
\ No newline at end of file
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+use ibus_sys::bindings::IBusEngine;
+
+use crate::context::AkazaContext;
+
+/// `ibus_akaza_commands_map` に登録する、コマンド名に対応づけられた関数。
+/// `run_callback_by_name` がキー入力から引いた名前をもとに、これを直接呼び出す。
+pub(crate) type IbusAkazaCommand = fn(&mut AkazaContext, *mut IBusEngine);
+
+/// コマンド名から `AkazaContext` のメソッドへのマッピングを作る。
+/// ここに載っていないコマンド名はキーに割り当てても何も起こらない。
+pub(crate) fn ibus_akaza_commands_map() -> HashMap<&'static str, IbusAkazaCommand> {
+    let mut map: HashMap<&'static str, IbusAkazaCommand> = HashMap::new();
+
+    map.insert("cycle_input_mode", AkazaContext::cycle_input_mode as IbusAkazaCommand);
+    map.insert(
+        "set_input_mode_hiragana",
+        AkazaContext::set_input_mode_hiragana as IbusAkazaCommand,
+    );
+    map.insert(
+        "set_input_mode_katakana",
+        AkazaContext::set_input_mode_katakana as IbusAkazaCommand,
+    );
+    map.insert(
+        "set_input_mode_hankaku_katakana",
+        AkazaContext::set_input_mode_hankaku_katakana as IbusAkazaCommand,
+    );
+    map.insert(
+        "set_input_mode_alnum",
+        AkazaContext::set_input_mode_alnum as IbusAkazaCommand,
+    );
+    map.insert("purge_candidate", AkazaContext::purge_candidate as IbusAkazaCommand);
+    map.insert(
+        "extend_current_clause",
+        AkazaContext::extend_current_clause as IbusAkazaCommand,
+    );
+    map.insert(
+        "shrink_current_clause",
+        AkazaContext::shrink_current_clause as IbusAkazaCommand,
+    );
+    map.insert("predict_completion", AkazaContext::predict_completion as IbusAkazaCommand);
+    map.insert("commit_prediction", AkazaContext::commit_prediction as IbusAkazaCommand);
+
+    map
+}
@@ -31,9 +31,73 @@ use crate::commands::{ibus_akaza_commands_map, IbusAkazaCommand};
 #[derive(Debug)]
 pub(crate) enum InputMode {
     Hiragana,
+    Katakana,
+    HankakuKatakana,
     Alnum,
 }
 
+impl InputMode {
+    /// 次の入力モードを返す。Ctrl+J 的なキーで入力モードを巡回させるのに使う。
+    pub(crate) fn next(&self) -> InputMode {
+        match self {
+            InputMode::Hiragana => InputMode::Katakana,
+            InputMode::Katakana => InputMode::HankakuKatakana,
+            InputMode::HankakuKatakana => InputMode::Alnum,
+            InputMode::Alnum => InputMode::Hiragana,
+        }
+    }
+}
+
+// ひらがな -> カタカナ の対応表。Unicode 上では片方だけ 0x60 ずらした位置にあるので、
+// コードポイントをずらすだけで変換できる(ぁ..ゖ の範囲)。
+fn hiragana_to_katakana(hiragana: &str) -> String {
+    hiragana
+        .chars()
+        .map(|c| {
+            if ('ぁ'..='ゖ').contains(&c) {
+                char::from_u32(c as u32 + 0x60).unwrap_or(c)
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+// 濁点・半濁点付きのカタカナは、半角カタカナ側には単独の文字が存在しないため、
+// 清音の半角カタカナ + 濁点/半濁点(U+FF9E/U+FF9F)の2文字に分解する。
+const HALFWIDTH_KATAKANA_MAP: &[(char, &str)] = &[
+    ('ア', "ｱ"), ('イ', "ｲ"), ('ウ', "ｳ"), ('エ', "ｴ"), ('オ', "ｵ"),
+    ('カ', "ｶ"), ('キ', "ｷ"), ('ク', "ｸ"), ('ケ', "ｹ"), ('コ', "ｺ"),
+    ('ガ', "ｶﾞ"), ('ギ', "ｷﾞ"), ('グ', "ｸﾞ"), ('ゲ', "ｹﾞ"), ('ゴ', "ｺﾞ"),
+    ('サ', "ｻ"), ('シ', "ｼ"), ('ス', "ｽ"), ('セ', "ｾ"), ('ソ', "ｿ"),
+    ('ザ', "ｻﾞ"), ('ジ', "ｼﾞ"), ('ズ', "ｽﾞ"), ('ゼ', "ｾﾞ"), ('ゾ', "ｿﾞ"),
+    ('タ', "ﾀ"), ('チ', "ﾁ"), ('ツ', "ﾂ"), ('テ', "ﾃ"), ('ト', "ﾄ"),
+    ('ダ', "ﾀﾞ"), ('ヂ', "ﾁﾞ"), ('ヅ', "ﾂﾞ"), ('デ', "ﾃﾞ"), ('ド', "ﾄﾞ"),
+    ('ナ', "ﾅ"), ('ニ', "ﾆ"), ('ヌ', "ﾇ"), ('ネ', "ﾈ"), ('ノ', "ﾉ"),
+    ('ハ', "ﾊ"), ('ヒ', "ﾋ"), ('フ', "ﾌ"), ('ヘ', "ﾍ"), ('ホ', "ﾎ"),
+    ('バ', "ﾊﾞ"), ('ビ', "ﾋﾞ"), ('ブ', "ﾌﾞ"), ('ベ', "ﾍﾞ"), ('ボ', "ﾎﾞ"),
+    ('パ', "ﾊﾟ"), ('ピ', "ﾋﾟ"), ('プ', "ﾌﾟ"), ('ペ', "ﾍﾟ"), ('ポ', "ﾎﾟ"),
+    ('マ', "ﾏ"), ('ミ', "ﾐ"), ('ム', "ﾑ"), ('メ', "ﾒ"), ('モ', "ﾓ"),
+    ('ヤ', "ﾔ"), ('ユ', "ﾕ"), ('ヨ', "ﾖ"),
+    ('ラ', "ﾗ"), ('リ', "ﾘ"), ('ル', "ﾙ"), ('レ', "ﾚ"), ('ロ', "ﾛ"),
+    ('ワ', "ﾜ"), ('ヲ', "ｦ"), ('ン', "ﾝ"), ('ヴ', "ｳﾞ"),
+    ('ッ', "ｯ"), ('ャ', "ｬ"), ('ュ', "ｭ"), ('ョ', "ｮ"),
+    ('ァ', "ｧ"), ('ィ', "ｨ"), ('ゥ', "ｩ"), ('ェ', "ｪ"), ('ォ', "ｫ"),
+    ('ー', "ｰ"), ('、', "､"), ('。', "｡"), ('「', "｢"), ('」', "｣"), ('・', "･"),
+];
+
+fn hiragana_to_hankaku_katakana(hiragana: &str) -> String {
+    let katakana = hiragana_to_katakana(hiragana);
+    let mut result = String::new();
+    for c in katakana.chars() {
+        match HALFWIDTH_KATAKANA_MAP.iter().find(|(k, _)| *k == c) {
+            Some((_, half)) => result.push_str(half),
+            None => result.push(c),
+        }
+    }
+    result
+}
+
 #[derive(Debug, Hash, PartialEq, Copy, Clone)]
 pub enum KeyState {
     // 何も入力されていない状態。
@@ -42,6 +106,10 @@ pub enum KeyState {
     Composition,
     // 変換中
     Conversion,
+    // 変換に入る前の、補完候補を lookup table に出している状態。
+    // `clauses` はまだ空のままなので、文節単位の操作(候補の確定/取り消しなど)は
+    // 対象にできない。
+    Prediction,
 }
 
 #[repr(C)]
@@ -56,6 +124,11 @@ pub struct AkazaContext {
     clauses: Vec<VecDeque<Candidate>>,
     // げんざいせんたくされているぶんせつ。
     current_clause: usize,
+    // ユーザーが Shift+Left/Shift+Right で手動調整した文節の区切り。
+    // (start, length) の組が、よみ全体を隙間なく敷き詰める。
+    force_selected_clause: Option<Vec<(usize, usize)>>,
+    // 変換前(ひらがな入力中)の補完候補。 lookup table の並びと対応する表記一覧。
+    predicted_surfaces: Vec<String>,
     is_invalidate: bool,
 }
 
@@ -144,6 +217,8 @@ impl AkazaContext {
             akaza,
             clauses: vec![],
             current_clause: 0,
+            force_selected_clause: None,
+            predicted_surfaces: vec![],
             is_invalidate: false,
         }
     }
@@ -155,10 +230,69 @@ impl Drop for AkazaContext {
     }
 }
 
+/// `slices` の `idx` 番目の文節と、そのとなりの境界を `delta` 文字ぶん動かす。
+/// となりの文節が無い、動かした結果どちらかの文節が最低0文字(もしくは1文字)を
+/// 下回る、といった動かせないケースでは `None` を返す。吸収しきってとなりが
+/// 0文字になったときは、2つの文節をひとつにまとめる。
+fn resize_clause_boundary(
+    slices: &[(usize, usize)],
+    idx: usize,
+    delta: i32,
+) -> Option<Vec<(usize, usize)>> {
+    // となりの文節がないと境界を動かせない。
+    if idx + 1 >= slices.len() {
+        return None;
+    }
+
+    let mut slices = slices.to_vec();
+    let (start, length) = slices[idx];
+    let (_, next_length) = slices[idx + 1];
+
+    let new_length = length as i32 + delta;
+    let new_next_length = next_length as i32 - delta;
+    // 自分は最低1文字、となりは最低0文字(=吸収による消滅)までしか縮められない。
+    if new_length < 1 || new_next_length < 0 {
+        return None;
+    }
+
+    if new_next_length == 0 {
+        // となりの文節をまるごと吸収して、ひとつの文節にまとめる。
+        slices[idx] = (start, new_length as usize);
+        slices.remove(idx + 1);
+    } else {
+        let next_start = start + new_length as usize;
+        slices[idx] = (start, new_length as usize);
+        slices[idx + 1] = (next_start, new_next_length as usize);
+    }
+
+    Some(slices)
+}
+
 impl AkazaContext {
     /**
      * 入力モードの変更
      */
+    // Python 版での元実装(参考):
+    //
+    // def _set_input_mode(self, mode: InputMode):
+    //     """
+    //
+    //     """
+    //     self.logger.info(f"input mode activate: {mode}")
+    //
+    //     # 変換候補をいったんコミットする。
+    //     self.commit_candidate()
+    //
+    //     label = _("Input mode (%s)") % mode.symbol
+    //     prop = self.input_mode_prop
+    //     prop.set_symbol(IBus.Text.new_from_string(mode.symbol))
+    //     prop.set_label(IBus.Text.new_from_string(label))
+    //     self.update_property(prop)
+    //
+    //     self.__prop_dict[mode.prop_name].set_state(IBus.PropState.CHECKED)
+    //     self.update_property(self.__prop_dict[mode.prop_name])
+    //
+    //     self.input_mode = mode
     pub(crate) fn set_input_mode(&mut self, input_mode: InputMode, engine: *mut IBusEngine) {
         info!("Changing input mode to : {:?}", input_mode);
 
@@ -168,28 +302,28 @@ impl AkazaContext {
         // TODO update menu prop
 
         self.input_mode = input_mode;
+    }
 
-        /*
-        def _set_input_mode(self, mode: InputMode):
-            """
-
-            """
-            self.logger.info(f"input mode activate: {mode}")
+    /// 入力モードを次のモードへ巡回させる。
+    pub(crate) fn cycle_input_mode(&mut self, engine: *mut IBusEngine) {
+        let next = self.input_mode.next();
+        self.set_input_mode(next, engine);
+    }
 
-            # 変換候補をいったんコミットする。
-            self.commit_candidate()
+    pub(crate) fn set_input_mode_hiragana(&mut self, engine: *mut IBusEngine) {
+        self.set_input_mode(InputMode::Hiragana, engine);
+    }
 
-            label = _("Input mode (%s)") % mode.symbol
-            prop = self.input_mode_prop
-            prop.set_symbol(IBus.Text.new_from_string(mode.symbol))
-            prop.set_label(IBus.Text.new_from_string(label))
-            self.update_property(prop)
+    pub(crate) fn set_input_mode_katakana(&mut self, engine: *mut IBusEngine) {
+        self.set_input_mode(InputMode::Katakana, engine);
+    }
 
-            self.__prop_dict[mode.prop_name].set_state(IBus.PropState.CHECKED)
-            self.update_property(self.__prop_dict[mode.prop_name])
+    pub(crate) fn set_input_mode_hankaku_katakana(&mut self, engine: *mut IBusEngine) {
+        self.set_input_mode(InputMode::HankakuKatakana, engine);
+    }
 
-            self.input_mode = mode
-             */
+    pub(crate) fn set_input_mode_alnum(&mut self, engine: *mut IBusEngine) {
+        self.set_input_mode(InputMode::Alnum, engine);
     }
 
     pub(crate) fn run_callback_by_name(
@@ -212,8 +346,12 @@ impl AkazaContext {
         if self.preedit.is_empty() {
             // 未入力状態。
             KeyState::PreComposition
-        } else if self.in_henkan_mode() {
+        } else if !self.clauses.is_empty() {
             KeyState::Conversion
+        } else if self.in_henkan_mode() {
+            // lookup table には候補があるが、文節(`clauses`)はまだ組み立てていない。
+            // `predict_completion` が出した補完候補が表示されているだけの状態。
+            KeyState::Prediction
         } else {
             KeyState::Composition
         }
@@ -230,6 +368,9 @@ impl AkazaContext {
             ibus_lookup_table_clear(self.lookup_table);
             ibus_engine_hide_preedit_text(engine);
         }
+        self.clauses = vec![];
+        self.current_clause = 0;
+        self.force_selected_clause = None;
 
         /*
             def commit_string(self, text):
@@ -298,13 +439,85 @@ impl AkazaContext {
         */
     }
 
+    /// いま候補ウィンドウでハイライトされている候補をユーザー辞書から削除し、
+    /// 二度とその誤変換が上位に出てこないようにする。
+    pub(crate) fn purge_candidate(&mut self, engine: *mut IBusEngine) {
+        if self.get_key_state() != KeyState::Conversion {
+            return;
+        }
+
+        let cursor_pos = unsafe { (*self.lookup_table).get_cursor_pos() } as usize;
+        let Some(candidate) = self.clauses[self.current_clause].get(cursor_pos) else {
+            return;
+        };
+
+        info!(
+            "Purging candidate: yomi={}, kanji={}",
+            candidate.yomi, candidate.kanji
+        );
+        if let Err(err) = self.akaza.purge_candidate(&candidate.yomi, &candidate.kanji) {
+            error!("Failed to purge candidate: {}", err);
+            return;
+        }
+
+        if let Err(err) = self._update_candidates(engine) {
+            error!("Failed to refresh candidates after purge: {}", err);
+        }
+    }
+
+    /// 変換(henkan)に入る前に、いま打っているよみの前方一致で単語全体を予測し、
+    /// lookup table に補完候補として並べる。
+    pub(crate) fn predict_completion(&mut self, engine: *mut IBusEngine) {
+        if self.in_henkan_mode() {
+            return;
+        }
+
+        let yomi = self.romkan.to_hiragana(self.preedit.as_str());
+        if yomi.is_empty() {
+            return;
+        }
+
+        let predictions = match self.akaza.predict(yomi.as_str()) {
+            Ok(predictions) => predictions,
+            Err(err) => {
+                error!("Failed to predict completion: {}", err);
+                return;
+            }
+        };
+
+        self.predicted_surfaces = predictions
+            .iter()
+            .filter_map(|(_yomi, surfaces)| surfaces.first().cloned())
+            .collect();
+
+        unsafe {
+            ibus_lookup_table_clear(self.lookup_table);
+            for surface in &self.predicted_surfaces {
+                ibus_lookup_table_append_candidate(self.lookup_table, surface.to_ibus_text());
+            }
+        }
+        self._update_lookup_table(engine);
+    }
+
+    /// 予測補完の lookup table でハイライトされている候補を、そのままコミットする。
+    pub(crate) fn commit_prediction(&mut self, engine: *mut IBusEngine) {
+        let cursor_pos = unsafe { (*self.lookup_table).get_cursor_pos() } as usize;
+        if let Some(surface) = self.predicted_surfaces.get(cursor_pos).cloned() {
+            self.commit_string(engine, surface.as_str());
+        }
+        self.predicted_surfaces.clear();
+    }
+
     fn _update_candidates(&mut self, engine: *mut IBusEngine) -> Result<()> {
         if self.preedit.is_empty() {
             self.clauses = vec![]
         } else {
-            // TODO support force selected.
-            self.clauses = self.akaza.convert(self.preedit.as_str(), &vec![])?;
+            let slices = self.force_selected_clause.clone().unwrap_or_default();
+            self.clauses = self.akaza.convert(self.preedit.as_str(), &slices)?;
         }
+        self.current_clause = self
+            .current_clause
+            .min(self.clauses.len().saturating_sub(1));
         self.create_lookup_table();
         self.refresh(engine);
         Ok(())
@@ -326,6 +539,44 @@ impl AkazaContext {
         */
     }
 
+    /// いまの分節わけを (start, length) のよみ文字数ぶんのスライス列として取り出す。
+    fn current_slices(&self) -> Vec<(usize, usize)> {
+        let mut slices = Vec::with_capacity(self.clauses.len());
+        let mut pos = 0;
+        for clause in &self.clauses {
+            let len = clause[0].yomi.chars().count();
+            slices.push((pos, len));
+            pos += len;
+        }
+        slices
+    }
+
+    /// Shift+Right 相当。現在の文節を1文字ぶん右にひろげ、となりの文節から1文字もらう。
+    pub(crate) fn extend_current_clause(&mut self, engine: *mut IBusEngine) {
+        self.resize_current_clause(1, engine);
+    }
+
+    /// Shift+Left 相当。現在の文節を1文字ぶん縮め、となりの文節に1文字わたす。
+    pub(crate) fn shrink_current_clause(&mut self, engine: *mut IBusEngine) {
+        self.resize_current_clause(-1, engine);
+    }
+
+    fn resize_current_clause(&mut self, delta: i32, engine: *mut IBusEngine) {
+        let idx = self.current_clause;
+        if self.clauses.is_empty() {
+            return;
+        }
+
+        let Some(slices) = resize_clause_boundary(&self.current_slices(), idx, delta) else {
+            return;
+        };
+
+        self.force_selected_clause = Some(slices);
+        if let Err(err) = self._update_candidates(engine) {
+            error!("Failed to re-convert after clause resize: {}", err);
+        }
+    }
+
     /**
      * 現在の候補選択状態から、 lookup table を構築する。
      */
@@ -423,7 +674,12 @@ impl AkazaContext {
         }
 
         let yomi = self.romkan.to_hiragana(preedit.as_str());
-        (yomi.clone(), yomi)
+        let word = match self.input_mode {
+            InputMode::Katakana => hiragana_to_katakana(&yomi),
+            InputMode::HankakuKatakana => hiragana_to_hankaku_katakana(&yomi),
+            InputMode::Hiragana | InputMode::Alnum => yomi.clone(),
+        };
+        (yomi, word)
 
         /*
             # 先頭が大文字だと、
@@ -440,4 +696,75 @@ impl AkazaContext {
                 return yomi, yomi
         */
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn katakana_converts_plain_and_dakuten_kana() {
+        assert_eq!(hiragana_to_katakana("ひらがな"), "ヒラガナ");
+        assert_eq!(hiragana_to_katakana("がぎぐげご"), "ガギグゲゴ");
+        assert_eq!(hiragana_to_katakana("ぱぴぷぺぽ"), "パピプペポ");
+        // 対応表の範囲外(ひらがなでない文字)はそのまま通す。
+        assert_eq!(hiragana_to_katakana("abc123"), "abc123");
+    }
+
+    #[test]
+    fn hankaku_katakana_decomposes_dakuten_and_handakuten() {
+        assert_eq!(hiragana_to_hankaku_katakana("がぎぐげご"), "ｶﾞｷﾞｸﾞｹﾞｺﾞ");
+        assert_eq!(hiragana_to_hankaku_katakana("ぱぴぷぺぽ"), "ﾊﾟﾋﾟﾌﾟﾍﾟﾎﾟ");
+        assert_eq!(hiragana_to_hankaku_katakana("ばびぶべぼ"), "ﾊﾞﾋﾞﾌﾞﾍﾞﾎﾞ");
+    }
+
+    #[test]
+    fn hankaku_katakana_maps_vu() {
+        // ヴ は半角カタカナに単独の文字が無いので、ｳﾞ (清音+濁点) に分解する。
+        assert_eq!(hiragana_to_hankaku_katakana("ゔ"), "ｳﾞ");
+    }
+
+    #[test]
+    fn hankaku_katakana_maps_long_vowel_and_punctuation() {
+        assert_eq!(hiragana_to_hankaku_katakana("ー、。「」・"), "ｰ､｡｢｣･");
+    }
+
+    #[test]
+    fn resize_clause_boundary_shrinks_and_grows_the_neighbor() {
+        let slices = vec![(0, 3), (3, 2)];
+        // Shift+Left 相当: 自分を縮め、となりを伸ばす。
+        assert_eq!(
+            resize_clause_boundary(&slices, 0, -1),
+            Some(vec![(0, 2), (2, 3)])
+        );
+        // Shift+Right 相当: 自分を伸ばし、となりを縮める。
+        assert_eq!(
+            resize_clause_boundary(&slices, 0, 1),
+            Some(vec![(0, 4), (4, 1)])
+        );
+    }
+
+    #[test]
+    fn resize_clause_boundary_merges_when_neighbor_is_absorbed() {
+        let slices = vec![(0, 3), (3, 1)];
+        assert_eq!(resize_clause_boundary(&slices, 0, 1), Some(vec![(0, 4)]));
+    }
+
+    #[test]
+    fn resize_clause_boundary_refuses_to_shrink_below_one_character() {
+        let slices = vec![(0, 1), (1, 2)];
+        assert_eq!(resize_clause_boundary(&slices, 0, -1), None);
+    }
+
+    #[test]
+    fn resize_clause_boundary_refuses_to_grow_past_the_neighbor() {
+        let slices = vec![(0, 3), (3, 1)];
+        assert_eq!(resize_clause_boundary(&slices, 0, 2), None);
+    }
+
+    #[test]
+    fn resize_clause_boundary_refuses_when_there_is_no_neighbor() {
+        let slices = vec![(0, 3)];
+        assert_eq!(resize_clause_boundary(&slices, 0, 1), None);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,247 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::graph::graph_resolver::Candidate;
+use crate::kana_kanji::base::KanaKanjiDict;
+use crate::kana_kanji::marisa_kana_kanji_dict::MarisaKanaKanjiDict;
+use crate::kana_trie::backend::load_any;
+use crate::kana_trie::base::KanaTrie;
+
+/// ユーザーが「この候補は出したくない」と指定した (よみ, 表記) のペアを覚えておく、
+/// 書き込み可能な上書き辞書。システム辞書(`MarisaKanaKanjiDict`)はファイルから
+/// 読み込むだけの読み取り専用なので、学習の取り消しはこちら側で行う。
+///
+/// `store_path` のファイルに `よみ\t表記` を1行ずつ追記していくだけの素朴な形式で、
+/// プロセスを再起動しても `load` で読み直せば覚えたままになる。
+#[derive(Default)]
+struct UserDict {
+    purged: HashMap<String, HashSet<String>>,
+    store_path: PathBuf,
+}
+
+impl UserDict {
+    /// `path` があれば読み込む。無ければ、まだ一度も purge していないだけなので
+    /// 空の状態から始める。
+    fn load(path: &Path) -> anyhow::Result<UserDict> {
+        let mut purged: HashMap<String, HashSet<String>> = HashMap::new();
+        if path.exists() {
+            for line in fs::read_to_string(path)?.lines() {
+                let Some((yomi, kanji)) = line.split_once('\t') else {
+                    continue;
+                };
+                purged
+                    .entry(yomi.to_string())
+                    .or_default()
+                    .insert(kanji.to_string());
+            }
+        }
+        Ok(UserDict {
+            purged,
+            store_path: path.to_path_buf(),
+        })
+    }
+
+    fn purge(&mut self, yomi: &str, kanji: &str) -> anyhow::Result<()> {
+        let newly_purged = self
+            .purged
+            .entry(yomi.to_string())
+            .or_default()
+            .insert(kanji.to_string());
+        if newly_purged {
+            self.append_to_store(yomi, kanji)?;
+        }
+        Ok(())
+    }
+
+    // 1エントリぶんを store_path に追記する。毎回 open/close するぶん遅いが、
+    // purge は頻繁な操作ではないので、確実に永続化されることを優先する。
+    fn append_to_store(&self, yomi: &str, kanji: &str) -> anyhow::Result<()> {
+        if let Some(parent) = self.store_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.store_path)?;
+        writeln!(file, "{}\t{}", yomi, kanji)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    fn is_purged(&self, yomi: &str, kanji: &str) -> bool {
+        self.purged
+            .get(yomi)
+            .map(|purged| purged.contains(kanji))
+            .unwrap_or(false)
+    }
+}
+
+/// 変換エンジンの本体。`AkazaContext` はキー入力のたびにこれを呼び出して、
+/// 変換候補の取得や、ユーザーによる候補の取り消しを行う。
+pub struct Akaza {
+    dict: MarisaKanaKanjiDict,
+    // よみの文節区切りを見つけるためのトライ。`KanaTrieBackend` を切り替えても
+    // ここから先は共通のトレイトごしに使うだけなので、エンジン側は気にしなくてよい。
+    kana_trie: Box<dyn KanaTrie>,
+    user_dict: UserDict,
+}
+
+impl Akaza {
+    /// `user_dict_path` に過去の purge 履歴があれば読み込んで引き継ぐ。
+    pub fn new(
+        dict: MarisaKanaKanjiDict,
+        kana_trie: Box<dyn KanaTrie>,
+        user_dict_path: &str,
+    ) -> anyhow::Result<Akaza> {
+        Ok(Akaza {
+            dict,
+            kana_trie,
+            user_dict: UserDict::load(Path::new(user_dict_path))?,
+        })
+    }
+
+    /// システム辞書とかな語トライをファイルから読み込む。トライは `load_any` 経由で
+    /// 読むので、保存時にどの `KanaTrieBackend` を選んでいても透過的に読み込める。
+    pub fn load(
+        system_dict_path: &str,
+        kana_trie_path: &str,
+        user_dict_path: &str,
+    ) -> anyhow::Result<Akaza> {
+        Akaza::new(
+            MarisaKanaKanjiDict::load(system_dict_path)?,
+            load_any(kana_trie_path)?,
+            user_dict_path,
+        )
+    }
+
+    /// `preedit` をかな漢字変換する。`slices` はユーザーが Shift+Left/Shift+Right で
+    /// 強制した文節区切りで、(文字数での start, length) の組がよみ全体を隙間なく
+    /// 敷き詰める。空でなければ、その区切りを文節の境界として固定し、各文節を独立に
+    /// 辞書引きする。
+    ///
+    /// `slices` が空のときは、よみ全体で辞書引きし、無ければトライで見つかる最長の
+    /// 前方一致を最初の文節、残りをひらがなのままの2文節目として扱う。
+    pub fn convert(
+        &self,
+        preedit: &str,
+        slices: &[(usize, usize)],
+    ) -> anyhow::Result<Vec<VecDeque<Candidate>>> {
+        if !slices.is_empty() {
+            let chars: Vec<char> = preedit.chars().collect();
+            return Ok(slices
+                .iter()
+                .map(|&(start, length)| {
+                    let yomi: String = chars[start..start + length].iter().collect();
+                    let mut candidates = self.lookup(&yomi);
+                    if candidates.is_empty() {
+                        candidates = self.passthrough(&yomi);
+                    }
+                    candidates
+                })
+                .collect());
+        }
+
+        let whole = self.lookup(preedit);
+        if !whole.is_empty() {
+            return Ok(vec![whole]);
+        }
+
+        let known = self.kana_trie.common_prefix_search(preedit);
+        let longest_known = known.iter().max_by_key(|yomi| yomi.chars().count());
+        let Some(head_yomi) = longest_known.filter(|yomi| yomi.as_str() != preedit) else {
+            return Ok(vec![self.passthrough(preedit)]);
+        };
+
+        let tail_yomi = &preedit[head_yomi.len()..];
+        let head = {
+            let mut candidates = self.lookup(head_yomi);
+            if candidates.is_empty() {
+                candidates = self.passthrough(head_yomi);
+            }
+            candidates
+        };
+        Ok(vec![head, self.passthrough(tail_yomi)])
+    }
+
+    fn lookup(&self, yomi: &str) -> VecDeque<Candidate> {
+        let mut surfaces = self.dict.get(yomi).unwrap_or_default();
+        surfaces.retain(|surface| !self.user_dict.is_purged(yomi, surface));
+        surfaces
+            .into_iter()
+            .map(|kanji| Candidate {
+                yomi: yomi.to_string(),
+                kanji,
+            })
+            .collect()
+    }
+
+    fn passthrough(&self, yomi: &str) -> VecDeque<Candidate> {
+        VecDeque::from([Candidate {
+            yomi: yomi.to_string(),
+            kanji: yomi.to_string(),
+        }])
+    }
+
+    /// `yomi`/`kanji` の組を、今後二度と候補に出さないようにする。
+    /// `user_dict` のストアに追記されるので、プロセスを再起動しても取り消したままになる。
+    pub fn purge_candidate(&mut self, yomi: &str, kanji: &str) -> anyhow::Result<()> {
+        self.user_dict.purge(yomi, kanji)
+    }
+
+    /// `prefix` で始まるよみを前方一致検索する。変換キーを確定する前の、
+    /// 補完候補の表示に使う。
+    pub fn predict(&self, prefix: &str) -> anyhow::Result<Vec<(String, Vec<String>)>> {
+        Ok(self.dict.predict(prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    #[test]
+    fn purge_marks_only_the_purged_pair() -> anyhow::Result<()> {
+        let tmpfile = NamedTempFile::new().unwrap();
+        let mut user_dict = UserDict::load(tmpfile.path())?;
+
+        assert!(!user_dict.is_purged("なかの", "中野"));
+        user_dict.purge("なかの", "中野")?;
+        assert!(user_dict.is_purged("なかの", "中野"));
+        // 同じよみの別の表記はそのまま。
+        assert!(!user_dict.is_purged("なかの", "仲野"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_from_missing_path_starts_empty() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("user_dict.tsv");
+
+        let user_dict = UserDict::load(&path)?;
+        assert!(!user_dict.is_purged("なかの", "中野"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn purge_persists_across_reload() -> anyhow::Result<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("user_dict.tsv");
+
+        let mut user_dict = UserDict::load(&path)?;
+        user_dict.purge("なかの", "中野")?;
+        drop(user_dict);
+
+        // プロセスを再起動した体で、同じパスからもう一度読み直す。
+        let reloaded = UserDict::load(&path)?;
+        assert!(reloaded.is_purged("なかの", "中野"));
+
+        Ok(())
+    }
+}
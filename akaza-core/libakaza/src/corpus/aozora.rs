@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::fs;
+
+use anyhow::Result;
+use encoding_rs::SHIFT_JIS;
+
+/// ルビの境界として扱うマーカー文字。最終的な出力には含まれない、内部処理専用の文字。
+/// これを挟むことで、「直前の連続する漢字」を数える処理が、すでに別のルビとして
+/// 消費済みの漢字まで誤ってさかのぼらないようにする。
+const RUBY_BOUNDARY: char = '\u{0}';
+
+/// 青空文庫のテキストファイルを読み込み、ルビから (よみ -> 表記一覧) を抽出する。
+/// 表記は、ファイル内での出現回数が多い順に並ぶ。
+pub fn import_aozora_file(path: &str) -> Result<HashMap<String, Vec<String>>> {
+    let bytes = fs::read(path)?;
+    let text = decode_aozora_text(&bytes);
+    Ok(import_aozora_text(&text))
+}
+
+/// 青空文庫は伝統的に Shift_JIS で配布されてきたが、近年は UTF-8 のファイルもある。
+/// 妥当な UTF-8 ならそのまま使い、そうでなければ Shift_JIS として読み直す。
+fn decode_aozora_text(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        Err(_) => {
+            let (cow, _, _had_errors) = SHIFT_JIS.decode(bytes);
+            cow.into_owned()
+        }
+    }
+}
+
+/// テキストからルビの (表記, よみ) のペアを全部ぬき出し、出現回数で重みをつけた
+/// (よみ -> 表記一覧) に集計する。
+pub fn import_aozora_text(text: &str) -> HashMap<String, Vec<String>> {
+    let mut counts: HashMap<(String, String), u32> = HashMap::new();
+    for (surface, yomi) in extract_ruby_pairs(text) {
+        if surface.is_empty() || yomi.is_empty() {
+            continue;
+        }
+        *counts.entry((yomi, surface)).or_insert(0) += 1;
+    }
+
+    let mut by_yomi: HashMap<String, Vec<(String, u32)>> = HashMap::new();
+    for ((yomi, surface), count) in counts {
+        by_yomi.entry(yomi).or_default().push((surface, count));
+    }
+
+    by_yomi
+        .into_iter()
+        .map(|(yomi, mut surfaces)| {
+            surfaces.sort_by(|a, b| b.1.cmp(&a.1));
+            (yomi, surfaces.into_iter().map(|(s, _)| s).collect())
+        })
+        .collect()
+}
+
+/// 青空文庫のルビ記法を解析する。
+///
+/// - `｜中野《なかの》` のように `｜` で始まる区間が表記、そのあとの `《…》` がよみ。
+/// - `｜` が無い場合、 `《…》` は直前の漢字の連続(＝最大限さかのぼった連続した漢字)にかかる。
+/// - `［＃…］` の編集注記・外字注記は読み飛ばす。
+fn extract_ruby_pairs(text: &str) -> Vec<(String, String)> {
+    let text = strip_editorial_annotations(text);
+    let chars: Vec<char> = text.chars().collect();
+    let mut pairs = Vec::new();
+    // ここまでに読んだ「地の文」。直前の漢字の連続をさかのぼって調べるためだけに使う。
+    let mut plain_buf = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '｜' => {
+                let (base, next) = take_until(&chars, i + 1, '《');
+                if let Some(open) = next {
+                    if let (yomi, Some(close)) = take_until(&chars, open + 1, '》') {
+                        pairs.push((base, normalize_yomi(&yomi)));
+                        plain_buf.push(RUBY_BOUNDARY);
+                        i = close + 1;
+                        continue;
+                    }
+                }
+                // 対応する《…》が無い壊れた記法。｜はそのまま読み飛ばす。
+                i += 1;
+            }
+            '《' => {
+                let (yomi, close) = take_until(&chars, i + 1, '》');
+                if let Some(close) = close {
+                    let kanji_run = trailing_kanji_run(&plain_buf);
+                    if !kanji_run.is_empty() {
+                        pairs.push((kanji_run, normalize_yomi(&yomi)));
+                    }
+                    plain_buf.push(RUBY_BOUNDARY);
+                    i = close + 1;
+                    continue;
+                }
+                i += 1;
+            }
+            c => {
+                plain_buf.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    pairs
+}
+
+/// `chars[start..]` を `end` の直前まで読み取る。`end` が見つからなければ `None`。
+fn take_until(chars: &[char], start: usize, end: char) -> (String, Option<usize>) {
+    let mut buf = String::new();
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == end {
+            return (buf, Some(i));
+        }
+        buf.push(chars[i]);
+        i += 1;
+    }
+    (buf, None)
+}
+
+fn is_kanji(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}')
+}
+
+fn trailing_kanji_run(plain_buf: &str) -> String {
+    let run: Vec<char> = plain_buf
+        .chars()
+        .rev()
+        .take_while(|c| is_kanji(*c))
+        .collect();
+    run.into_iter().rev().collect()
+}
+
+/// ［＃…］ の形をした編集注記・外字注記を取りのぞく。
+fn strip_editorial_annotations(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '［' && chars.get(i + 1) == Some(&'＃') {
+            if let Some(end) = chars[i..].iter().position(|&c| c == '］') {
+                i += end + 1;
+                continue;
+            }
+        }
+        result.push(chars[i]);
+        i += 1;
+    }
+    result
+}
+
+/// よみを平仮名に正規化する。全角カタカナは平仮名へ、全角数字は半角数字へそろえる。
+fn normalize_yomi(yomi: &str) -> String {
+    yomi.chars()
+        .map(|c| {
+            if ('ァ'..='ヶ').contains(&c) {
+                char::from_u32(c as u32 - 0x60).unwrap_or(c)
+            } else if ('０'..='９').contains(&c) {
+                char::from_u32(0x30 + (c as u32 - 0xff10)).unwrap_or(c)
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_explicit_ruby() {
+        let pairs = extract_ruby_pairs("｜中野《なかの》さんの家《いえ》");
+        assert_eq!(
+            pairs,
+            vec![
+                ("中野".to_string(), "なかの".to_string()),
+                ("家".to_string(), "いえ".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn adjacent_bare_ruby_does_not_merge_kanji_runs() {
+        let pairs = extract_ruby_pairs("矢《や》車《ぐるま》");
+        assert_eq!(
+            pairs,
+            vec![
+                ("矢".to_string(), "や".to_string()),
+                ("車".to_string(), "ぐるま".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn explicit_ruby_does_not_leak_into_following_bare_ruby() {
+        let pairs = extract_ruby_pairs("｜東京《とうきょう》都《と》");
+        assert_eq!(
+            pairs,
+            vec![
+                ("東京".to_string(), "とうきょう".to_string()),
+                ("都".to_string(), "と".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_editorial_and_gaiji_annotations() {
+        let pairs = extract_ruby_pairs("｜吃驚《びっくり》［＃「吃驚」に傍点］した");
+        assert_eq!(pairs, vec![("吃驚".to_string(), "びっくり".to_string())]);
+    }
+
+    #[test]
+    fn normalizes_katakana_and_fullwidth_digit_readings() {
+        let pairs = extract_ruby_pairs("｜令和《レイワ》｜三十《３０》年");
+        assert_eq!(
+            pairs,
+            vec![
+                ("令和".to_string(), "れいわ".to_string()),
+                ("三十".to_string(), "30".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn import_counts_and_ranks_surfaces_by_frequency() {
+        let dict = import_aozora_text("｜家《うち》｜内《うち》｜家《うち》｜家《いえ》");
+        assert_eq!(
+            dict.get("うち"),
+            Some(&vec!["家".to_string(), "内".to_string()])
+        );
+        assert_eq!(dict.get("いえ"), Some(&vec!["家".to_string()]));
+    }
+}
@@ -1,9 +1,70 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 
 use log::trace;
 
 use crate::graph::lattice_graph::LatticeGraph;
 use crate::graph::word_node::WordNode;
+use crate::lm::system_trigram::SystemTrigramLM;
+
+// backward A* 探索でキューが際限なくふくらまないようにする上限。
+const NBEST_QUEUE_CAPACITY: usize = 10_000;
+
+// stupid backoff で trigram が見つからないときに、 bigram のスコアにかけるペナルティ。
+// 加算している値は log(0.4) 。
+const STUPID_BACKOFF_LOG_WEIGHT: f32 = -0.916_290_7;
+
+// backward A* 探索のキューに積む、未完成(EOS から途中まで)のパス。
+struct PartialPath<'a> {
+    // BOS から現ノードまでの最良到達コスト(costmap)を足した、見積もり優先度。
+    priority: f32,
+    node: &'a WordNode,
+    // すでに組み立てた、このノードから __EOS__ までのコスト。
+    suffix_score: f32,
+    // __EOS__ からこのノードまで、たどってきたノードを順番に積んだもの。
+    path: Vec<&'a WordNode>,
+}
+
+impl PartialEq for PartialPath<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl Eq for PartialPath<'_> {}
+
+impl PartialOrd for PartialPath<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PartialPath<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap は max-heap なので、優先度が高いものがさきに pop される。
+        self.priority
+            .partial_cmp(&other.priority)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+// ヒープが上限を超えたら、優先度が低いものから間引く。
+fn prune_queue(heap: BinaryHeap<PartialPath>, cap: usize) -> BinaryHeap<PartialPath> {
+    if heap.len() <= cap {
+        return heap;
+    }
+    let mut sorted = heap.into_sorted_vec(); // 昇順(優先度が低い順)
+    let keep_from = sorted.len() - cap;
+    BinaryHeap::from(sorted.split_off(keep_from))
+}
+
+/// ある文節の、よみと変換後の表記のペア。`GraphResolver` が返す1文の結果とは別に、
+/// 文節単位で候補を出し分けたいクライアント( `AkazaContext` の文節変換 UI など)向け。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub yomi: String,
+    pub kanji: String,
+}
 
 // 次に必要なのは、分割された文字列から、グラフを構築する仕組みである。
 #[derive(Default)]
@@ -77,6 +138,235 @@ impl GraphResolver {
         result.reverse();
         Ok(result.join(""))
     }
+
+    /// 上位 `k` 件の変換候補を、スコアの高い順に返す。
+    ///
+    /// 1st pass は `viterbi` と同じ forward Viterbi で、 `__BOS__` から各ノードへの
+    /// 最良到達コスト(costmap)を埋める。2nd pass は `__EOS__` から `__BOS__` へ向かう
+    /// backward A* で、キューの優先度に costmap を「まだ見ぬ残り区間の見積もりコスト」
+    /// として足すことで、スコアの高い経路から順に完成させていく。
+    /// 同じ表記になる経路は重複させず、1件にまとめる。
+    pub fn resolve_nbest(&self, lattice: &LatticeGraph, k: usize) -> Vec<(String, f32)> {
+        let yomi = &lattice.yomi;
+        let mut costmap: HashMap<&WordNode, f32> = HashMap::new();
+
+        for i in 1..yomi.len() + 2 {
+            let Some(nodes) = &lattice.node_list(i as i32) else {
+                continue;
+            };
+            for node in *nodes {
+                let node_cost = lattice.get_node_cost(node);
+                let prev_nodes = lattice.get_prev_nodes(node).unwrap_or_else(|| {
+                    panic!(
+                        "Cannot get prev nodes for '{}' start={}",
+                        node.kanji, node.start_pos
+                    )
+                });
+                let mut cost = f32::MIN;
+                for prev in prev_nodes {
+                    let edge_cost = lattice.get_edge_cost(prev, node);
+                    let prev_cost = costmap.get(prev).unwrap_or(&0_f32);
+                    let tmp_cost = prev_cost + edge_cost + node_cost;
+                    if cost < tmp_cost {
+                        cost = tmp_cost;
+                    }
+                }
+                costmap.insert(node, cost);
+            }
+        }
+
+        let eos = lattice
+            .get((yomi.len() + 1) as i32)
+            .unwrap()
+            .get(0)
+            .unwrap();
+        let bos = lattice.get(0).unwrap().get(0).unwrap();
+
+        let mut heap: BinaryHeap<PartialPath> = BinaryHeap::new();
+        let eos_cost = lattice.get_node_cost(eos);
+        heap.push(PartialPath {
+            priority: costmap.get(eos).copied().unwrap_or(0_f32),
+            node: eos,
+            suffix_score: eos_cost,
+            path: vec![eos],
+        });
+
+        let mut results: Vec<(String, f32)> = Vec::new();
+        let mut seen_surfaces: HashSet<String> = HashSet::new();
+
+        while let Some(current) = heap.pop() {
+            if current.node == bos {
+                let surface: String = current
+                    .path
+                    .iter()
+                    .rev()
+                    .filter(|n| n.kanji != "__BOS__" && n.kanji != "__EOS__")
+                    .map(|n| n.kanji.as_str())
+                    .collect();
+                if seen_surfaces.insert(surface.clone()) {
+                    results.push((surface, current.suffix_score));
+                    if results.len() >= k {
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            let prev_nodes = lattice.get_prev_nodes(current.node).unwrap_or_else(|| {
+                panic!("Cannot get prev nodes for '{}'", current.node.kanji)
+            });
+            for prev in prev_nodes {
+                let edge_cost = lattice.get_edge_cost(prev, current.node);
+                let prev_node_cost = lattice.get_node_cost(prev);
+                let suffix_score = current.suffix_score + edge_cost + prev_node_cost;
+                // costmap[prev] も prev 自身のノードコストを含むので、二重に数えないよう引いておく。
+                let priority =
+                    suffix_score - prev_node_cost + costmap.get(prev).copied().unwrap_or(0_f32);
+                let mut path = current.path.clone();
+                path.push(prev);
+                heap.push(PartialPath {
+                    priority,
+                    node: prev,
+                    suffix_score,
+                    path,
+                });
+            }
+
+            if heap.len() > NBEST_QUEUE_CAPACITY {
+                heap = prune_queue(heap, NBEST_QUEUE_CAPACITY);
+            }
+        }
+
+        results
+    }
+
+    /// `trigram_lm` が渡されたときは 3-gram を使って変換する。`None` のときは
+    /// 従来どおり `viterbi` (bigram のみ) にフォールバックする。
+    ///
+    /// 3-gram を使うと、 DP の状態が「直前の1ノード」だけでは足りず、「直前の2ノード
+    /// (prev_prev, prev)」のペアに広がる。そのぶんラティス上で見るべき組み合わせが
+    /// 増えるので、 `viterbi` よりコストが高い。 `prev_prev` との 3-gram が見つからない
+    /// ときは、 stupid backoff で bigram のスコアにペナルティ(`STUPID_BACKOFF_LOG_WEIGHT`)
+    /// を足したものを使う。
+    pub fn viterbi_with_trigram(
+        &self,
+        lattice: &LatticeGraph,
+        trigram_lm: Option<&SystemTrigramLM>,
+    ) -> anyhow::Result<String> {
+        let Some(trigram_lm) = trigram_lm else {
+            return self.viterbi(lattice);
+        };
+
+        let yomi = &lattice.yomi;
+        // 状態は (1つ前のノード, 現在のノード) のペア。 bigram 版の costmap/prevmap が
+        // ノード単体をキーにしていたのに対し、こちらはその組を丸ごとキーにする。
+        type State<'a> = (&'a WordNode, &'a WordNode);
+        let mut costmap: HashMap<State, f32> = HashMap::new();
+        let mut backptr: HashMap<State, Option<State>> = HashMap::new();
+
+        let bos = lattice.get(0).unwrap().get(0).unwrap();
+
+        for i in 1..yomi.len() + 2 {
+            let Some(nodes) = &lattice.node_list(i as i32) else {
+                continue;
+            };
+            for node in *nodes {
+                let node_cost = lattice.get_node_cost(node);
+                let prev_nodes = lattice.get_prev_nodes(node).unwrap_or_else(|| {
+                    panic!(
+                        "Cannot get prev nodes for '{}' start={}",
+                        node.kanji, node.start_pos
+                    )
+                });
+
+                for prev in prev_nodes {
+                    if prev == bos {
+                        // (__BOS__, node) は、まだ2つ前のノードを持たない最初の状態。
+                        let edge_cost = edge_cost_with_backoff(lattice, trigram_lm, None, prev, node);
+                        let cost = edge_cost + node_cost;
+                        let state = (prev, node);
+                        if cost > *costmap.get(&state).unwrap_or(&f32::MIN) {
+                            costmap.insert(state, cost);
+                            backptr.insert(state, None);
+                        }
+                        continue;
+                    }
+
+                    let prev_prev_nodes = lattice.get_prev_nodes(prev).unwrap_or_else(|| {
+                        panic!("Cannot get prev nodes for '{}'", prev.kanji)
+                    });
+                    for prev_prev in prev_prev_nodes {
+                        let prev_state = (prev_prev, prev);
+                        let Some(&history_cost) = costmap.get(&prev_state) else {
+                            continue;
+                        };
+                        let edge_cost =
+                            edge_cost_with_backoff(lattice, trigram_lm, Some(prev_prev), prev, node);
+                        let tmp_cost = history_cost + edge_cost + node_cost;
+
+                        let state = (prev, node);
+                        if tmp_cost > *costmap.get(&state).unwrap_or(&f32::MIN) {
+                            costmap.insert(state, tmp_cost);
+                            backptr.insert(state, Some(prev_state));
+                        }
+                    }
+                }
+            }
+        }
+
+        let eos = lattice
+            .get((yomi.len() + 1) as i32)
+            .unwrap()
+            .get(0)
+            .unwrap();
+
+        let best_state = costmap
+            .iter()
+            .filter(|((_, node), _)| *node == eos)
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+            .map(|(state, _)| *state)
+            .unwrap_or_else(|| panic!("Cannot find a path to __EOS__"));
+
+        let mut result: Vec<String> = Vec::new();
+        let mut state = Some(best_state);
+        while let Some((prev, node)) = state {
+            if node.kanji != "__EOS__" {
+                result.push(node.kanji.to_string());
+            }
+            if prev == bos {
+                break;
+            }
+            state = backptr
+                .get(&(prev, node))
+                .copied()
+                .unwrap_or_else(|| panic!("Cannot get previous state for '{}'", node.kanji));
+        }
+        result.reverse();
+        Ok(result.join(""))
+    }
+}
+
+/// `prev` から `node` への遷移コストを求める。 `prev_prev` との 3-gram があれば
+/// そのスコアを、なければ bigram のスコア(`get_edge_cost`)に stupid backoff の
+/// ペナルティを足したものを使う。 `prev_prev` が無い(`prev` が `__BOS__`)ときは
+/// 3-gram を参照しようがないので、bigram のスコアをそのまま使う。
+fn edge_cost_with_backoff(
+    lattice: &LatticeGraph,
+    trigram_lm: &SystemTrigramLM,
+    prev_prev: Option<&WordNode>,
+    prev: &WordNode,
+    node: &WordNode,
+) -> f32 {
+    let bigram_cost = lattice.get_edge_cost(prev, node);
+    let Some(prev_prev) = prev_prev else {
+        return bigram_cost;
+    };
+
+    // WordNode が語彙上の ID (unigram 辞書での ID) を word_id() で返すことを前提にしている。
+    match trigram_lm.find(prev_prev.word_id(), prev.word_id(), node.word_id()) {
+        Some(score) => score,
+        None => bigram_cost + STUPID_BACKOFF_LOG_WEIGHT,
+    }
 }
 
 #[cfg(test)]
@@ -187,4 +477,56 @@ mod tests {
         let result = resolver.viterbi(&lattice).unwrap();
         assert_eq!(result, "私");
     }
+
+    #[test]
+    fn test_resolve_nbest() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut builder = KanaTrieBuilder::default();
+        builder.add(&"わたし".to_string());
+        builder.add(&"わた".to_string());
+        builder.add(&"し".to_string());
+        let kana_trie = builder.build();
+
+        let graph_builder = Segmenter::new(vec![kana_trie]);
+        let graph = graph_builder.build("わたし");
+
+        let mut dict_builder = KanaKanjiDictBuilder::default();
+        dict_builder.add("わたし", "私/渡し");
+
+        let yomi = "わたし".to_string();
+
+        let dict = dict_builder.build();
+        let system_unigram_lm_builder = SystemUnigramLMBuilder::default();
+        let system_unigram_lm = system_unigram_lm_builder.build();
+        let system_bigram_lm_builder = SystemBigramLMBuilder::default();
+        let system_bigram_lm = system_bigram_lm_builder.build();
+        let mut user_data = UserData::default();
+        // 私/わたし のスコアをガッと上げる。
+        user_data.record_entries(vec!["私/わたし".to_string()]);
+        let graph_builder = GraphBuilder::new(
+            dict,
+            Rc::new(user_data),
+            Rc::new(system_unigram_lm),
+            Rc::new(system_bigram_lm),
+        );
+        let lattice = graph_builder.construct(&yomi, graph);
+        let resolver = GraphResolver::default();
+
+        let best = resolver.viterbi(&lattice).unwrap();
+        let nbest = resolver.resolve_nbest(&lattice, 3);
+
+        // 1位は viterbi の結果とおなじでなければならない。
+        assert_eq!(nbest.first().map(|(s, _)| s.as_str()), Some(best.as_str()));
+        // スコアが降順にならんでいること。
+        for pair in nbest.windows(2) {
+            assert!(pair[0].1 >= pair[1].1);
+        }
+        // 表記で重複がないこと。
+        let mut surfaces: Vec<&String> = nbest.iter().map(|(s, _)| s).collect();
+        let len_before_dedup = surfaces.len();
+        surfaces.sort();
+        surfaces.dedup();
+        assert_eq!(surfaces.len(), len_before_dedup);
+    }
 }
\ No newline at end of file
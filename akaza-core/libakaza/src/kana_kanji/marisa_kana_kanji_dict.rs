@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+
+use log::info;
+
+use marisa_sys::{Keyset, Marisa};
+
+use crate::kana_kanji::base::KanaKanjiDict;
+
+#[derive(Default)]
+pub struct MarisaKanaKanjiDict {
+    marisa: Marisa,
+}
+
+impl MarisaKanaKanjiDict {
+    pub(crate) fn build(
+        dicts: HashMap<String, Vec<String>>,
+        cache_path: &str,
+    ) -> anyhow::Result<MarisaKanaKanjiDict> {
+        let mut keyset = Keyset::default();
+        for (kana, surfaces) in dicts {
+            keyset.push_back(
+                [
+                    kana.as_bytes(),
+                    b"\t", // seperator
+                    surfaces.join("/").as_bytes(),
+                ]
+                .concat()
+                .as_slice(),
+            );
+        }
+
+        let mut marisa = Marisa::default();
+        marisa.build(&keyset);
+        marisa.save(cache_path)?;
+        Ok(MarisaKanaKanjiDict { marisa })
+    }
+
+    pub fn load(file_name: &str) -> anyhow::Result<MarisaKanaKanjiDict> {
+        let mut marisa = Marisa::default();
+        marisa.load(file_name)?;
+        Ok(MarisaKanaKanjiDict { marisa })
+    }
+
+    /// `prefix` で始まるよみを前方一致検索し、(よみ, 表記一覧) のペアを返す。
+    /// 変換キーが確定する前の、補完候補の表示に使う。
+    pub fn predict(&self, prefix: &str) -> Vec<(String, Vec<String>)> {
+        let mut predictions: Vec<(String, Vec<String>)> = Vec::new();
+
+        self.marisa
+            .predictive_search(prefix.as_bytes(), |word, _| {
+                let Some(idx) = word.iter().position(|f| *f == b'\t') else {
+                    return true;
+                };
+                let yomi = String::from_utf8_lossy(&word[0..idx]).to_string();
+                let surfaces = String::from_utf8_lossy(&word[idx + 1..word.len()])
+                    .split('/')
+                    .map(|s| s.to_string())
+                    .collect();
+                predictions.push((yomi, surfaces));
+                true
+            });
+
+        predictions
+    }
+
+    pub fn yomis(&self) -> Vec<String> {
+        let mut yomis: Vec<String> = Vec::new();
+
+        self.marisa.predictive_search("".as_bytes(), |word, _| {
+            let idx = word.iter().position(|f| *f == b'\t').unwrap();
+            yomis.push(String::from_utf8_lossy(&word[0..idx]).to_string());
+            true
+        });
+
+        yomis
+    }
+}
+
+impl MarisaKanaKanjiDict {
+    fn lookup(&self, kana: &str) -> Vec<String> {
+        let mut surfaces: Vec<String> = Vec::new();
+        let query = [kana.as_bytes(), b"\t".as_slice()].concat();
+        self.marisa.predictive_search(query.as_slice(), |word, _| {
+            let idx = word.iter().position(|f| *f == b'\t').unwrap();
+            let s = String::from_utf8_lossy(&word[idx + 1..word.len()]).to_string();
+            for s in s.split('/').collect::<Vec<_>>() {
+                surfaces.push(s.to_string());
+            }
+            false
+        });
+        surfaces
+    }
+}
+
+impl KanaKanjiDict for MarisaKanaKanjiDict {
+    fn get(&self, kana: &str) -> Option<Vec<String>> {
+        let (key, digits) = normalize_numeric_key(kana);
+        if digits.is_empty() {
+            let surfaces = self.lookup(kana);
+            info!("Got result: {:?}, {:?}", kana, surfaces);
+            return Some(surfaces);
+        }
+
+        // 数字をひとつの `#` に正規化したキーで辞書をひき、
+        // `#0`/`#1`/... のようなマーカーを含むテンプレート候補を実際の数字列に展開する。
+        let mut surfaces: Vec<String> = Vec::new();
+        for template in self.lookup(&key) {
+            match expand_numeric_template(&template, &digits) {
+                Some(expanded) => surfaces.push(expanded),
+                None => surfaces.push(template),
+            }
+        }
+
+        // 辞書にマーカー付きの候補が無くても、数字そのものの代表的な書式は出しておく。
+        if key.chars().all(|c| c == NUMERIC_PLACEHOLDER) {
+            if let Some(run) = digits.first() {
+                for marker in ['0', '1', '2', '3', '8'] {
+                    if let Some(expanded) = format_by_marker(marker, run) {
+                        if !surfaces.contains(&expanded) {
+                            surfaces.push(expanded);
+                        }
+                    }
+                }
+            }
+        }
+
+        info!("Got result: {:?}, {:?}", kana, surfaces);
+        Some(surfaces)
+    }
+}
+
+const NUMERIC_PLACEHOLDER: char = '#';
+const KANJI_DIGITS: [char; 10] = ['〇', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+const SMALL_UNITS: [&str; 4] = ["", "十", "百", "千"];
+const BIG_UNITS: [&str; 5] = ["", "万", "億", "兆", "京"];
+
+/// よみの中の連続する数字列をそれぞれ `#` 一文字に正規化して、辞書引き用のキーを作る。
+/// 切り出した数字列は出現順に `digits` として返す。
+fn normalize_numeric_key(kana: &str) -> (String, Vec<String>) {
+    let mut key = String::new();
+    let mut digits: Vec<String> = Vec::new();
+    let mut current_run = String::new();
+    for c in kana.chars() {
+        if c.is_ascii_digit() {
+            current_run.push(c);
+        } else {
+            if !current_run.is_empty() {
+                digits.push(std::mem::take(&mut current_run));
+                key.push(NUMERIC_PLACEHOLDER);
+            }
+            key.push(c);
+        }
+    }
+    if !current_run.is_empty() {
+        digits.push(current_run);
+        key.push(NUMERIC_PLACEHOLDER);
+    }
+    (key, digits)
+}
+
+/// `template` 中の `#0`..`#8` マーカーを、出現順に `digits` を消費しながら展開する。
+/// マーカーが `digits` の本数より多い、未知のマーカーがある、といった展開できない場合は
+/// `None` を返す。マーカーが `digits` より少ないときは、余った数字列を使わずに展開する。
+fn expand_numeric_template(template: &str, digits: &[String]) -> Option<String> {
+    let mut result = String::new();
+    let mut chars = template.chars().peekable();
+    let mut digit_index = 0;
+    while let Some(c) = chars.next() {
+        if c == NUMERIC_PLACEHOLDER {
+            let marker = chars.next()?;
+            let run = digits.get(digit_index)?;
+            result.push_str(&format_by_marker(marker, run)?);
+            digit_index += 1;
+        } else {
+            result.push(c);
+        }
+    }
+    Some(result)
+}
+
+fn format_by_marker(marker: char, digits: &str) -> Option<String> {
+    match marker {
+        '0' => Some(digits.to_string()),
+        '1' => Some(to_fullwidth_digits(digits)),
+        '2' => Some(to_kanji_digits_naive(digits)),
+        '3' => Some(to_kanji_digits_positional(digits)),
+        '8' => Some(to_comma_grouped(digits)),
+        _ => None,
+    }
+}
+
+fn to_fullwidth_digits(digits: &str) -> String {
+    digits
+        .chars()
+        .map(|c| char::from_u32(0xff10 + (c as u32 - '0' as u32)).unwrap())
+        .collect()
+}
+
+fn to_kanji_digits_naive(digits: &str) -> String {
+    digits
+        .chars()
+        .map(|c| KANJI_DIGITS[(c as u8 - b'0') as usize])
+        .collect()
+}
+
+/// 千・百・十や万・億などの単位をともなう、位取り表記の漢数字に変換する。
+fn to_kanji_digits_positional(digits: &str) -> String {
+    let trimmed = digits.trim_start_matches('0');
+    if trimmed.is_empty() {
+        return "〇".to_string();
+    }
+
+    // 下位から4桁ずつのグループに分ける。groups[0] が一の位を含むグループ。
+    let chars: Vec<char> = trimmed.chars().collect();
+    let mut groups: Vec<&[char]> = Vec::new();
+    let mut i = chars.len();
+    while i > 0 {
+        let start = i.saturating_sub(4);
+        groups.push(&chars[start..i]);
+        i = start;
+    }
+
+    let mut result = String::new();
+    for (big_index, group) in groups.iter().enumerate().rev() {
+        let group_value = convert_four_digit_group(group);
+        if group_value.is_empty() {
+            continue;
+        }
+        result.push_str(&group_value);
+        if big_index > 0 {
+            // `BIG_UNITS` は京(10^16)までしか名前を持たない。それより大きい桁は
+            // 単位を付けられないので、パニックさせずに単位なしで連結するだけにとどめる。
+            if let Some(unit) = BIG_UNITS.get(big_index) {
+                result.push_str(unit);
+            }
+        }
+    }
+    result
+}
+
+fn convert_four_digit_group(group: &[char]) -> String {
+    let len = group.len();
+    let mut result = String::new();
+    for (idx, c) in group.iter().enumerate() {
+        let digit = (*c as u8 - b'0') as usize;
+        if digit == 0 {
+            continue;
+        }
+        let place = len - idx - 1; // 0 = 一の位, 1 = 十, 2 = 百, 3 = 千
+        if place > 0 && digit == 1 {
+            // 「一十」ではなく「十」、「一百」ではなく「百」のように、位が1のときは単位だけにする。
+            result.push_str(SMALL_UNITS[place]);
+        } else {
+            result.push(KANJI_DIGITS[digit]);
+            result.push_str(SMALL_UNITS[place]);
+        }
+    }
+    result
+}
+
+fn to_comma_grouped(digits: &str) -> String {
+    let trimmed = digits.trim_start_matches('0');
+    let trimmed = if trimmed.is_empty() { "0" } else { trimmed };
+    let chars: Vec<char> = trimmed.chars().collect();
+    let mut result = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if i > 0 && (chars.len() - i) % 3 == 0 {
+            result.push(',');
+        }
+        result.push(*c);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    #[test]
+    fn write_read() -> anyhow::Result<()> {
+        let tmpfile = NamedTempFile::new().unwrap();
+        let path = tmpfile.path().to_str().unwrap().to_string();
+
+        let dict = MarisaKanaKanjiDict::build(
+            HashMap::from([("たなか".to_string(), vec!["田中".to_string()])]),
+            path.as_str(),
+        )?;
+
+        assert_eq!(dict.get("たなか"), Some(vec!["田中".to_string()]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn predict_returns_words_starting_with_prefix() -> anyhow::Result<()> {
+        let tmpfile = NamedTempFile::new().unwrap();
+        let path = tmpfile.path().to_str().unwrap().to_string();
+
+        let dict = MarisaKanaKanjiDict::build(
+            HashMap::from([
+                ("たなか".to_string(), vec!["田中".to_string()]),
+                ("たなばた".to_string(), vec!["七夕".to_string()]),
+                ("さとう".to_string(), vec!["佐藤".to_string()]),
+            ]),
+            path.as_str(),
+        )?;
+
+        let mut predictions = dict.predict("たな");
+        predictions.sort();
+        assert_eq!(
+            predictions,
+            vec![
+                ("たなか".to_string(), vec!["田中".to_string()]),
+                ("たなばた".to_string(), vec!["七夕".to_string()]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn numeric_key_is_normalized_and_expanded() -> anyhow::Result<()> {
+        let tmpfile = NamedTempFile::new().unwrap();
+        let path = tmpfile.path().to_str().unwrap().to_string();
+
+        let dict = MarisaKanaKanjiDict::build(
+            HashMap::from([("#がつ".to_string(), vec!["#0月".to_string()])]),
+            path.as_str(),
+        )?;
+
+        assert_eq!(dict.get("12がつ"), Some(vec!["12月".to_string()]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn plain_digit_reading_offers_all_numeric_styles() -> anyhow::Result<()> {
+        let tmpfile = NamedTempFile::new().unwrap();
+        let path = tmpfile.path().to_str().unwrap().to_string();
+
+        let dict = MarisaKanaKanjiDict::build(HashMap::new(), path.as_str())?;
+
+        assert_eq!(
+            dict.get("1234"),
+            Some(vec![
+                "1234".to_string(),
+                "１２３４".to_string(),
+                "一二三四".to_string(),
+                "千二百三十四".to_string(),
+                "1,234".to_string(),
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn positional_kanji_handles_zero() {
+        assert_eq!(to_kanji_digits_positional("0"), "〇");
+        assert_eq!(to_kanji_digits_positional("10"), "十");
+        assert_eq!(to_kanji_digits_positional("20030"), "二万三十");
+    }
+
+    #[test]
+    fn positional_kanji_does_not_panic_beyond_big_units() {
+        // BIG_UNITS は京(10^16)までしか名前を持たない。21桁(10^20)の入力は
+        // 対応する単位が無い最上位グループを生むが、パニックせずに
+        // 単位なしで連結するだけにとどめる。
+        let digits = format!("1{}", "0".repeat(20));
+        assert_eq!(to_kanji_digits_positional(&digits), "一");
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fs;
+
+use anyhow::Result;
+use encoding_rs::EUC_JP;
+use log::warn;
+
+use crate::kana_kanji::base::KanaKanjiDict;
+
+/// SKK-JISYO.L などの SKK 形式辞書をそのまま読み込んで引ける `KanaKanjiDict`。
+///
+/// okuri-ari/okuri-nasi の区別はせず、 `よみ -> 表記一覧` として読み込む。
+/// `MarisaKanaKanjiDict::build` が食べる `HashMap<String, Vec<String>>` と同じ形を
+/// `entries()` で取り出せるので、marisa キャッシュへのコンパイルにも使い回せる。
+#[derive(Default)]
+pub struct SkkJisyoDict {
+    entries: HashMap<String, Vec<String>>,
+    // (よみ, 表記) -> 注釈。 `;` のうしろについている補足テキスト。
+    annotations: HashMap<(String, String), String>,
+}
+
+impl SkkJisyoDict {
+    pub fn load(file_name: &str) -> Result<SkkJisyoDict> {
+        let bytes = fs::read(file_name)?;
+        let text = decode_skk_jisyo(&bytes);
+        let (entries, annotations) = parse_skk_jisyo(&text);
+        Ok(SkkJisyoDict {
+            entries,
+            annotations,
+        })
+    }
+
+    /// `MarisaKanaKanjiDict::build` にそのまま渡せる形で取り出す。
+    pub fn entries(&self) -> HashMap<String, Vec<String>> {
+        self.entries.clone()
+    }
+
+    pub fn annotation(&self, yomi: &str, surface: &str) -> Option<&String> {
+        self.annotations
+            .get(&(yomi.to_string(), surface.to_string()))
+    }
+}
+
+impl KanaKanjiDict for SkkJisyoDict {
+    fn get(&self, kana: &str) -> Option<Vec<String>> {
+        self.entries.get(kana).cloned()
+    }
+}
+
+// SKK-JISYO は歴史的に EUC-JP で配布されてきたが、近年の辞書は UTF-8 のものも多い。
+// 妥当な UTF-8 ならそのまま使い、そうでなければ EUC-JP として読み直す。
+fn decode_skk_jisyo(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        Err(_) => {
+            let (cow, _, had_errors) = EUC_JP.decode(bytes);
+            if had_errors {
+                warn!("SKK-JISYO file is neither valid UTF-8 nor valid EUC-JP");
+            }
+            cow.into_owned()
+        }
+    }
+}
+
+/// `よみ /候補1/候補2;注釈/候補3/` の1行をパースする。
+/// 戻り値は (よみ, [(表記, 注釈)]) 。
+fn parse_entry_line(line: &str) -> Option<(String, Vec<(String, Option<String>)>)> {
+    if line.is_empty() || line.starts_with(';') {
+        return None;
+    }
+    let (yomi, candidates) = line.split_once(' ')?;
+    let candidates = candidates.trim_matches('/');
+    if candidates.is_empty() {
+        return None;
+    }
+
+    let mut result = Vec::new();
+    for cand in candidates.split('/') {
+        if cand.is_empty() {
+            continue;
+        }
+        let (surface, annotation) = match cand.split_once(';') {
+            Some((surface, annotation)) => (surface.to_string(), Some(annotation.to_string())),
+            None => (cand.to_string(), None),
+        };
+        result.push((surface, annotation));
+    }
+    Some((yomi.to_string(), result))
+}
+
+fn parse_skk_jisyo(
+    text: &str,
+) -> (
+    HashMap<String, Vec<String>>,
+    HashMap<(String, String), String>,
+) {
+    let mut dict: HashMap<String, Vec<String>> = HashMap::new();
+    let mut annotations: HashMap<(String, String), String> = HashMap::new();
+
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        // ";; okuri-ari entries." / ";; okuri-nasi entries." はセクションの区切り
+        // コメントで、変換候補としては両方同じ辞書にまとめてしまってよい。
+        if line.starts_with(";;") {
+            continue;
+        }
+        let Some((yomi, candidates)) = parse_entry_line(line) else {
+            continue;
+        };
+        let surfaces = dict.entry(yomi.clone()).or_default();
+        for (surface, annotation) in candidates {
+            if !surfaces.contains(&surface) {
+                surfaces.push(surface.clone());
+            }
+            if let Some(annotation) = annotation {
+                annotations.insert((yomi.clone(), surface), annotation);
+            }
+        }
+    }
+
+    (dict, annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_okuri_nasi_entry() {
+        let (dict, annotations) = parse_skk_jisyo(
+            ";; okuri-ari entries.\n;; okuri-nasi entries.\nよみ /候補1/候補2;注釈/候補3/\n",
+        );
+        assert_eq!(
+            dict.get("よみ"),
+            Some(&vec![
+                "候補1".to_string(),
+                "候補2".to_string(),
+                "候補3".to_string()
+            ])
+        );
+        assert_eq!(
+            annotations.get(&("よみ".to_string(), "候補2".to_string())),
+            Some(&"注釈".to_string())
+        );
+    }
+
+    #[test]
+    fn skips_comment_and_blank_lines() {
+        let (dict, _) = parse_skk_jisyo(";; okuri-ari entries.\n\n;comment\nかな /漢字/\n");
+        assert_eq!(dict.get("かな"), Some(&vec!["漢字".to_string()]));
+        assert_eq!(dict.len(), 1);
+    }
+
+    #[test]
+    fn merges_duplicate_surfaces_across_sections() {
+        let (dict, _) = parse_skk_jisyo(
+            ";; okuri-ari entries.\nあ /亜/\n;; okuri-nasi entries.\nあ /亜/安/\n",
+        );
+        assert_eq!(dict.get("あ"), Some(&vec!["亜".to_string(), "安".to_string()]));
+    }
+}
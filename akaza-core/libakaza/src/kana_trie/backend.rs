@@ -0,0 +1,117 @@
+use std::fs;
+use std::time::{Duration, Instant};
+
+use crate::kana_trie::base::KanaTrie;
+use crate::kana_trie::crawdad_kana_trie::CrawdadKanaTrie;
+use crate::kana_trie::marisa_kana_trie::MarisaKanaTrie;
+
+/// このプロセスで選べる `KanaTrie` の実装。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KanaTrieBackend {
+    /// crawdad のダブル配列トライ。もとからのデフォルト実装。
+    Crawdad,
+    /// marisa-trie ベースの実装。
+    Marisa,
+}
+
+// ファイル先頭に置く、バックエンドを見分けるためのマジックナンバー。
+const CRAWDAD_MAGIC: &[u8] = b"AKZKTC1\0";
+const MARISA_MAGIC: &[u8] = b"AKZKTM1\0";
+
+impl KanaTrieBackend {
+    fn magic(self) -> &'static [u8] {
+        match self {
+            KanaTrieBackend::Crawdad => CRAWDAD_MAGIC,
+            KanaTrieBackend::Marisa => MARISA_MAGIC,
+        }
+    }
+}
+
+/// `backend` でキーからトライを組み立て、先頭にバックエンドを示すマジックナンバーを
+/// 付けて `path` に保存する。`load_any` はこのマジックを見て読み込み先を選ぶ。
+pub fn save_any(backend: KanaTrieBackend, keys: Vec<String>, path: &str) -> anyhow::Result<()> {
+    let tmpfile = tempfile::NamedTempFile::new()?;
+    let tmp_path = tmpfile.path().to_str().unwrap();
+    match backend {
+        KanaTrieBackend::Crawdad => CrawdadKanaTrie::build(keys)?.save(tmp_path)?,
+        KanaTrieBackend::Marisa => MarisaKanaTrie::build(keys)?.save(tmp_path)?,
+    }
+
+    let mut out = backend.magic().to_vec();
+    out.extend_from_slice(&fs::read(tmp_path)?);
+    fs::write(path, out)?;
+    Ok(())
+}
+
+/// ファイルを読み、先頭のマジックナンバーをもとに適切な `KanaTrie` 実装へ振り分ける。
+/// `CrawdadKanaTrie::load` 等をそれぞれ直接呼ぶかわりに、ここ一箇所で済ませられる。
+///
+/// マジックナンバーが見つからないファイルは、このしくみができる前から使われてきた、
+/// ヘッダを持たない crawdad 形式として読み込む。
+pub fn load_any(path: &str) -> anyhow::Result<Box<dyn KanaTrie>> {
+    let bytes = fs::read(path)?;
+    if let Some(payload) = bytes.strip_prefix(MARISA_MAGIC) {
+        return Ok(Box::new(MarisaKanaTrie::from_bytes(payload)?));
+    }
+    if let Some(payload) = bytes.strip_prefix(CRAWDAD_MAGIC) {
+        return Ok(Box::new(CrawdadKanaTrie::from_bytes(payload)?));
+    }
+    Ok(Box::new(CrawdadKanaTrie::from_bytes(&bytes)?))
+}
+
+/// `trie` に対する `common_prefix_search` の所要時間を計測する。おなじ辞書を
+/// 異なるバックエンドで読み込んで比較する、ベンチマーク用のヘルパー。
+pub fn bench_common_prefix_search(trie: &dyn KanaTrie, query: &str) -> (Vec<String>, Duration) {
+    let start = Instant::now();
+    let result = trie.common_prefix_search(query);
+    (result, start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    fn sample_keys() -> Vec<String> {
+        vec![
+            "わたし".to_string(),
+            "わた".to_string(),
+            "わし".to_string(),
+            "ほげほげ".to_string(),
+        ]
+    }
+
+    #[test]
+    fn load_any_round_trips_each_backend() -> anyhow::Result<()> {
+        for backend in [KanaTrieBackend::Crawdad, KanaTrieBackend::Marisa] {
+            let tmpfile = NamedTempFile::new().unwrap();
+            let path = tmpfile.path().to_str().unwrap().to_string();
+
+            save_any(backend, sample_keys(), &path)?;
+            let trie = load_any(&path)?;
+
+            assert_eq!(
+                trie.common_prefix_search("わたしのきもち"),
+                vec!["わた", "わたし"]
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn load_any_falls_back_to_crawdad_for_legacy_headerless_files() -> anyhow::Result<()> {
+        let tmpfile = NamedTempFile::new().unwrap();
+        let path = tmpfile.path().to_str().unwrap().to_string();
+
+        // マジックナンバーができる前の、ヘッダの無い crawdad ファイルを模している。
+        CrawdadKanaTrie::build(sample_keys())?.save(&path)?;
+
+        let trie = load_any(&path)?;
+        assert_eq!(
+            trie.common_prefix_search("わたしのきもち"),
+            vec!["わた", "わたし"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+use crawdad::Trie;
+
+use crate::kana_trie::base::KanaTrie;
+
+pub struct CrawdadKanaTrie {
+    trie: Trie,
+    // 読み込んだファイル/バイト列のサイズ。バックエンド同士を比較するベンチマーク用。
+    // `build` で組み立てたものは、まだファイルになっていないので 0 のまま。
+    byte_size: usize,
+}
+
+impl Default for CrawdadKanaTrie {
+    fn default() -> Self {
+        let keys: Vec<String> = Vec::from(["DDDDDDDDDDDDDDDDDUMMY_FOR_TESTING".to_string()]);
+        let trie = Trie::from_keys(keys).unwrap();
+        CrawdadKanaTrie { trie, byte_size: 0 }
+    }
+}
+
+impl CrawdadKanaTrie {
+    pub fn load(file_name: &str) -> anyhow::Result<CrawdadKanaTrie> {
+        let file = File::open(file_name)?;
+        let mut buf: Vec<u8> = Vec::new();
+        BufReader::new(file).read_to_end(&mut buf)?;
+        Self::from_bytes(buf.as_slice())
+    }
+
+    /// すでにメモリ上にある、ヘッダの無い crawdad のシリアライズ表現からトライを組み立てる。
+    /// `load_any` がマジックナンバーを読み飛ばしたあとの残りを渡すのにも使う。
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<CrawdadKanaTrie> {
+        let (trie, _) = crawdad::Trie::deserialize_from_slice(bytes);
+        Ok(CrawdadKanaTrie {
+            trie,
+            byte_size: bytes.len(),
+        })
+    }
+
+    pub fn build(keys: Vec<String>) -> anyhow::Result<CrawdadKanaTrie> {
+        let trie = Trie::from_keys(keys).unwrap();
+        Ok(CrawdadKanaTrie { trie, byte_size: 0 })
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        std::fs::write(path, self.trie.serialize_to_vec())?;
+        Ok(())
+    }
+
+    /// 読み込んだトライのバイト数。バックエンド同士のサイズを比較するのに使う。
+    pub fn byte_size(&self) -> usize {
+        self.byte_size
+    }
+}
+
+impl KanaTrie for CrawdadKanaTrie {
+    fn common_prefix_search(&self, query: &str) -> Vec<String> {
+        let haystack: Vec<char> = query.chars().collect();
+        // 文字位置 -> バイトオフセットの対応表を先に1回だけ作っておく。
+        // ヒットのたびに `query.char_indices().nth(s)` を呼ぶと、ヒット数に対して
+        // O(n) の再走査になってしまう(= 全体で O(n*k))ので、それを避ける。
+        let mut byte_offsets: Vec<usize> = Vec::with_capacity(haystack.len() + 1);
+        let mut offset = 0;
+        for c in &haystack {
+            byte_offsets.push(offset);
+            offset += c.len_utf8();
+        }
+        byte_offsets.push(offset);
+
+        self.trie
+            .common_prefix_search(haystack.iter().copied())
+            .map(|(_, s)| query[0..byte_offsets[s]].to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hello() -> anyhow::Result<()> {
+        let trie = CrawdadKanaTrie::build(vec![
+            "わたし".to_string(),
+            "わた".to_string(),
+            "わし".to_string(),
+            "ほげほげ".to_string(),
+        ])?;
+        assert_eq!(
+            trie.common_prefix_search("わたしのきもち"),
+            vec!("わた", "わたし")
+        );
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,106 @@
+use std::fs;
+
+use marisa_sys::{Keyset, Marisa};
+use tempfile::NamedTempFile;
+
+use crate::kana_trie::base::KanaTrie;
+
+/// `CrawdadKanaTrie` の代わりに選べる、 marisa-trie ベースの `KanaTrie` 実装。
+/// crawdad のようにネイティブな共通接頭辞検索は持たないので、読みを1文字ずつ
+/// 伸ばしながら完全一致を引きなおす、より素朴なやりかたで実装している。
+pub struct MarisaKanaTrie {
+    marisa: Marisa,
+    // 読み込んだファイルのサイズ。バックエンド同士を比較するベンチマーク用。
+    byte_size: usize,
+}
+
+impl MarisaKanaTrie {
+    pub fn build(keys: Vec<String>) -> anyhow::Result<MarisaKanaTrie> {
+        let mut keyset = Keyset::default();
+        for key in &keys {
+            keyset.push_back(key.as_bytes());
+        }
+        let mut marisa = Marisa::default();
+        marisa.build(&keyset);
+        Ok(MarisaKanaTrie {
+            marisa,
+            byte_size: 0,
+        })
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<MarisaKanaTrie> {
+        let mut marisa = Marisa::default();
+        marisa.load(path)?;
+        let byte_size = fs::metadata(path)?.len() as usize;
+        Ok(MarisaKanaTrie { marisa, byte_size })
+    }
+
+    /// すでにメモリ上にある、ヘッダの無い marisa のシリアライズ表現からトライを組み立てる。
+    /// `load_any` がマジックナンバーを読み飛ばしたあとの残りを渡すのにも使う。
+    ///
+    /// marisa_sys はファイルパス経由の読み書きしか提供していないので、いったん
+    /// 一時ファイルへ書き出してから読み直している。
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<MarisaKanaTrie> {
+        let tmpfile = NamedTempFile::new()?;
+        fs::write(tmpfile.path(), bytes)?;
+        MarisaKanaTrie::load(tmpfile.path().to_str().unwrap())
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        self.marisa.save(path)?;
+        Ok(())
+    }
+
+    /// 読み込んだトライのバイト数。バックエンド同士のサイズを比較するのに使う。
+    pub fn byte_size(&self) -> usize {
+        self.byte_size
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        let mut found = false;
+        self.marisa.predictive_search(key.as_bytes(), |word, _| {
+            if word == key.as_bytes() {
+                found = true;
+                false
+            } else {
+                true
+            }
+        });
+        found
+    }
+}
+
+impl KanaTrie for MarisaKanaTrie {
+    fn common_prefix_search(&self, query: &str) -> Vec<String> {
+        let mut result = Vec::new();
+        let mut byte_len = 0;
+        for c in query.chars() {
+            byte_len += c.len_utf8();
+            let candidate = &query[0..byte_len];
+            if self.contains(candidate) {
+                result.push(candidate.to_string());
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hello() -> anyhow::Result<()> {
+        let trie = MarisaKanaTrie::build(vec![
+            "わたし".to_string(),
+            "わた".to_string(),
+            "わし".to_string(),
+            "ほげほげ".to_string(),
+        ])?;
+        assert_eq!(
+            trie.common_prefix_search("わたしのきもち"),
+            vec!("わた", "わたし")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use marisa_sys::{Keyset, Marisa};
+
+use crate::lm::varint::write_varint;
+
+/// 単語 ID のペア `(word_id1, word_id2)` をキーにした 2-gram スコアのビルダー。
+/// ID はそれぞれ LEB128 の可変長整数で詰めるので、 `SystemUnigramLMBuilder` と同様に
+/// かつての 3 byte 固定幅(語彙数 2^23 までという上限)を持たない。
+#[derive(Default)]
+pub struct SystemBigramLMBuilder {
+    entries: Vec<(i32, i32, f32)>,
+}
+
+impl SystemBigramLMBuilder {
+    pub fn new() -> Self {
+        SystemBigramLMBuilder::default()
+    }
+
+    pub fn add(&mut self, word_id1: i32, word_id2: i32, score: f32) {
+        self.entries.push((word_id1, word_id2, score));
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        let mut marisa = Marisa::default();
+        marisa.build(&self.build_keyset());
+        marisa.save(path)?;
+        Ok(())
+    }
+
+    /// 単体テスト用に、ファイルを経由せずそのまま読み込み可能な LM を作る。
+    pub fn build(&self) -> SystemBigramLM {
+        let mut marisa = Marisa::default();
+        marisa.build(&self.build_keyset());
+        SystemBigramLM { marisa }
+    }
+
+    fn build_keyset(&self) -> Keyset {
+        let mut keyset = Keyset::default();
+        for (id1, id2, score) in &self.entries {
+            keyset.push_back(bigram_key(*id1, *id2, Some(*score)).as_slice());
+        }
+        keyset
+    }
+}
+
+/// 保存済みの 2-gram トライを読み込み、 `(id1, id2)` からスコアをひく。
+pub struct SystemBigramLM {
+    marisa: Marisa,
+}
+
+impl SystemBigramLM {
+    pub fn load(path: &str) -> anyhow::Result<SystemBigramLM> {
+        let mut marisa = Marisa::default();
+        marisa.load(path)?;
+        Ok(SystemBigramLM { marisa })
+    }
+
+    pub fn find(&self, word_id1: i32, word_id2: i32) -> Option<f32> {
+        let prefix = bigram_key(word_id1, word_id2, None);
+        let mut score = None;
+        self.marisa.predictive_search(prefix.as_slice(), |entry, _| {
+            let payload = &entry[prefix.len()..];
+            score = Some(f32::from_le_bytes(payload[0..4].try_into().unwrap()));
+            false
+        });
+        score
+    }
+}
+
+// ID 二つぶんの varint を先頭に詰め、スコアを渡したときはそのうしろに
+// リトルエンディアンの 4 byte を連結した、トライに積む実際のキーを作る。
+// ID は常に非負なので、 varint には符号なしとして詰める。
+fn bigram_key(id1: i32, id2: i32, score: Option<f32>) -> Vec<u8> {
+    let mut key = Vec::new();
+    write_varint(id1 as u64, &mut key);
+    write_varint(id2 as u64, &mut key);
+    if let Some(score) = score {
+        key.extend_from_slice(&score.to_le_bytes());
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    #[test]
+    fn write_read() -> anyhow::Result<()> {
+        let tmpfile = NamedTempFile::new().unwrap();
+        let path = tmpfile.path().to_str().unwrap().to_string();
+
+        let mut builder = SystemBigramLMBuilder::new();
+        builder.add(1, 2, -1.5);
+        builder.add(1, 3, -2.5);
+        builder.save(path.as_str())?;
+
+        let lm = SystemBigramLM::load(path.as_str())?;
+        assert_eq!(lm.find(1, 2), Some(-1.5));
+        assert_eq!(lm.find(1, 3), Some(-2.5));
+        assert_eq!(lm.find(1, 4), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ids_past_the_old_3_byte_ceiling_still_round_trip() -> anyhow::Result<()> {
+        let tmpfile = NamedTempFile::new().unwrap();
+        let path = tmpfile.path().to_str().unwrap().to_string();
+
+        // かつての 3 byte 固定幅 (2^23 = 8,388,608) をまたぐ ID の組み合わせ。
+        let low_id = 8_388_607;
+        let high_id = 8_388_608;
+
+        let mut builder = SystemBigramLMBuilder::new();
+        builder.add(low_id, high_id, -3.0);
+        builder.add(high_id, high_id + 1, -4.0);
+        builder.save(path.as_str())?;
+
+        let lm = SystemBigramLM::load(path.as_str())?;
+        assert_eq!(lm.find(low_id, high_id), Some(-3.0));
+        assert_eq!(lm.find(high_id, high_id + 1), Some(-4.0));
+        assert_eq!(lm.find(low_id, low_id), None);
+
+        Ok(())
+    }
+}
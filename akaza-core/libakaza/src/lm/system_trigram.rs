@@ -0,0 +1,90 @@
+use marisa_sys::{Keyset, Marisa};
+
+/// 3-gram (`word1\tword2\tword3 score`) のテキストファイルから、 unigram の ID 三つ組を
+/// キーにしたトライを組み立てるビルダー。 `SystemBigramLMBuilder` の 3-gram 版。
+#[derive(Default)]
+pub struct SystemTrigramLMBuilder {
+    entries: Vec<(u32, u32, u32, f32)>,
+}
+
+impl SystemTrigramLMBuilder {
+    pub fn new() -> Self {
+        SystemTrigramLMBuilder::default()
+    }
+
+    pub fn add(&mut self, word_id1: u32, word_id2: u32, word_id3: u32, score: f32) {
+        self.entries.push((word_id1, word_id2, word_id3, score));
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        let mut keyset = Keyset::default();
+        for (id1, id2, id3, score) in &self.entries {
+            keyset.push_back(trigram_key(*id1, *id2, *id3, Some(*score)).as_bytes());
+        }
+
+        let mut marisa = Marisa::default();
+        marisa.build(&keyset);
+        marisa.save(path)?;
+        Ok(())
+    }
+}
+
+/// 保存済みの 3-gram トライを読み込み、 `(id1, id2, id3)` からスコアをひく。
+pub struct SystemTrigramLM {
+    marisa: Marisa,
+}
+
+impl SystemTrigramLM {
+    pub fn load(path: &str) -> anyhow::Result<SystemTrigramLM> {
+        let mut marisa = Marisa::default();
+        marisa.load(path)?;
+        Ok(SystemTrigramLM { marisa })
+    }
+
+    pub fn find(&self, word_id1: u32, word_id2: u32, word_id3: u32) -> Option<f32> {
+        let prefix = trigram_key(word_id1, word_id2, word_id3, None);
+        let mut score = None;
+        self.marisa.predictive_search(prefix.as_bytes(), |word, _| {
+            let s = String::from_utf8_lossy(word);
+            if let Some(score_str) = s.strip_prefix(prefix.as_str()) {
+                score = score_str.parse::<f32>().ok();
+            }
+            false
+        });
+        score
+    }
+}
+
+// `id1\tid2\tid3\t` をキーのプレフィックスとし、スコアを渡したときはそのうしろに
+// 文字列化したスコアを連結した、トライに積む実際のキーを作る。
+fn trigram_key(id1: u32, id2: u32, id3: u32, score: Option<f32>) -> String {
+    match score {
+        Some(score) => format!("{}\t{}\t{}\t{}", id1, id2, id3, score),
+        None => format!("{}\t{}\t{}\t", id1, id2, id3),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    #[test]
+    fn write_read() -> anyhow::Result<()> {
+        let tmpfile = NamedTempFile::new().unwrap();
+        let path = tmpfile.path().to_str().unwrap().to_string();
+
+        let mut builder = SystemTrigramLMBuilder::new();
+        builder.add(1, 2, 3, -1.5);
+        builder.add(1, 2, 4, -2.5);
+        builder.save(path.as_str())?;
+
+        let lm = SystemTrigramLM::load(path.as_str())?;
+        assert_eq!(lm.find(1, 2, 3), Some(-1.5));
+        assert_eq!(lm.find(1, 2, 4), Some(-2.5));
+        assert_eq!(lm.find(1, 2, 5), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use marisa_sys::{Keyset, Marisa};
+
+use crate::lm::varint::{read_varint, write_varint};
+
+/// 単語をキーに、語彙 ID とスコアを組み立てるビルダー。語彙 ID は追加順に採番する。
+/// ID は LEB128 の可変長整数で詰めるので、かつての 3 byte 固定幅(語彙数 2^23 =
+/// 8,388,608 が上限)と違い、語彙が増えてもそのままスケールする。
+#[derive(Default)]
+pub struct SystemUnigramLMBuilder {
+    entries: Vec<(String, f32)>,
+}
+
+impl SystemUnigramLMBuilder {
+    pub fn new() -> Self {
+        SystemUnigramLMBuilder::default()
+    }
+
+    pub fn add(&mut self, word: &String, score: f32) {
+        self.entries.push((word.clone(), score));
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        let mut marisa = Marisa::default();
+        marisa.build(&self.build_keyset());
+        marisa.save(path)?;
+        Ok(())
+    }
+
+    /// 単体テスト用に、ファイルを経由せずそのまま読み込み可能な LM を作る。
+    pub fn build(&self) -> SystemUnigramLM {
+        let mut marisa = Marisa::default();
+        marisa.build(&self.build_keyset());
+        SystemUnigramLM { marisa }
+    }
+
+    fn build_keyset(&self) -> Keyset {
+        let mut keyset = Keyset::default();
+        for (id, (word, score)) in self.entries.iter().enumerate() {
+            keyset.push_back(unigram_key(word, id as u64, Some(*score)).as_slice());
+        }
+        keyset
+    }
+}
+
+/// 保存済みの unigram トライを読み込み、単語から `(語彙 ID, スコア)` をひく。
+pub struct SystemUnigramLM {
+    marisa: Marisa,
+}
+
+impl SystemUnigramLM {
+    pub fn load(path: &str) -> anyhow::Result<SystemUnigramLM> {
+        let mut marisa = Marisa::default();
+        marisa.load(path)?;
+        Ok(SystemUnigramLM { marisa })
+    }
+
+    pub fn num_keys(&self) -> usize {
+        self.marisa.num_keys()
+    }
+
+    pub fn find(&self, word: &String) -> Option<(u32, f32)> {
+        let query = [word.as_bytes(), b"\t"].concat();
+        let mut result = None;
+        self.marisa.predictive_search(query.as_slice(), |entry, _| {
+            let payload = &entry[query.len()..];
+            let (id, consumed) = read_varint(payload);
+            let score = f32::from_le_bytes(payload[consumed..consumed + 4].try_into().unwrap());
+            result = Some((id as u32, score));
+            false
+        });
+        result
+    }
+}
+
+// `word\t` をキーのプレフィックスとし、スコアを渡したときはそのうしろに
+// varint 化した語彙 ID と、リトルエンディアン 4 byte のスコアを連結した、
+// トライに積む実際のキーを作る。
+fn unigram_key(word: &str, id: u64, score: Option<f32>) -> Vec<u8> {
+    let mut key = word.as_bytes().to_vec();
+    key.push(b'\t');
+    if let Some(score) = score {
+        write_varint(id, &mut key);
+        key.extend_from_slice(&score.to_le_bytes());
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    #[test]
+    fn write_read() -> anyhow::Result<()> {
+        let tmpfile = NamedTempFile::new().unwrap();
+        let path = tmpfile.path().to_str().unwrap().to_string();
+
+        let mut builder = SystemUnigramLMBuilder::new();
+        builder.add(&"私".to_string(), -1.5);
+        builder.add(&"渡し".to_string(), -2.5);
+        builder.save(path.as_str())?;
+
+        let lm = SystemUnigramLM::load(path.as_str())?;
+        assert_eq!(lm.find(&"私".to_string()), Some((0, -1.5)));
+        assert_eq!(lm.find(&"渡し".to_string()), Some((1, -2.5)));
+        assert_eq!(lm.find(&"知らない".to_string()), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn key_encoding_round_trips_ids_past_the_old_3_byte_ceiling() {
+        // かつての 3 byte 固定幅 (2^23 = 8,388,608) をまたぐ語彙 ID でも、
+        // キーの組み立て方さえ正しければ崩れないことを確認する。
+        for id in [8_388_607u64, 8_388_608, 20_000_000] {
+            let key = unigram_key("単語", id, Some(-1.0));
+            let payload = &key["単語".len() + 1..];
+            let (decoded_id, consumed) = read_varint(payload);
+            assert_eq!(decoded_id, id);
+            let score = f32::from_le_bytes(payload[consumed..consumed + 4].try_into().unwrap());
+            assert_eq!(score, -1.0);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! LEB128 形式の可変長整数のエンコード/デコード。7bit ずつ値を詰め、続きが
+//! あるバイトの最上位ビットを 1 にする。小さい ID は 1 byte に収まり、固定幅
+//! だった頃のような上限(3 byte = 2^23 語彙)なしに語彙数をスケールさせられる。
+
+/// `value` を LEB128 でエンコードし、`out` の末尾に追記する。
+pub(crate) fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let mut byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value != 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+/// `bytes` の先頭から LEB128 整数を1つ読み取り、`(値, 消費したバイト数)` を返す。
+pub(crate) fn read_varint(bytes: &[u8]) -> (u64, usize) {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return (value, i + 1);
+        }
+        shift += 7;
+    }
+    panic!("truncated varint");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_small_and_large_values() {
+        for value in [
+            0u64,
+            1,
+            127,
+            128,
+            300,
+            8_388_607,
+            8_388_608,
+            20_000_000,
+            u32::MAX as u64,
+        ] {
+            let mut buf = Vec::new();
+            write_varint(value, &mut buf);
+            let (decoded, consumed) = read_varint(&buf);
+            assert_eq!(decoded, value);
+            assert_eq!(consumed, buf.len());
+        }
+    }
+
+    #[test]
+    fn small_ids_cost_a_single_byte() {
+        let mut buf = Vec::new();
+        write_varint(42, &mut buf);
+        assert_eq!(buf.len(), 1);
+    }
+}